@@ -24,8 +24,15 @@
  *
  */
 
-use clap::{Parser, ValueEnum};
+use base64::Engine;
+use clap::{Parser, Subcommand, ValueEnum};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::combinator::{all_consuming, map_res};
+use nom::sequence::preceded;
+use nom::IResult;
 use std::error::Error;
+use std::io::{Read, Write};
 use twenty_first::{math::tip5::Tip5, prelude::{Digest, BFieldElement}};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
@@ -34,85 +41,409 @@ enum Mode {
     Varlen,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Endian {
+    Big,
+    Little,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Hex,
+    Base64,
+    Base32,
+    Decimal,
+    Binary,
+    RawBytes,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start an interactive REPL for repeated hashing
+    Interactive,
+}
+
 #[derive(Parser)]
 #[command(author, version, about = "TIP5 Hash Calculator")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Hash mode: 'pair' or 'varlen'
     #[arg(short, long, value_enum, default_value_t = Mode::Pair)]
     mode: Mode,
 
-    /// Input numbers (hex with 0x prefix, decimal, or octal with 0 prefix)
-    #[arg(required = true, help = "Input numbers.\nFor pair mode: provide exactly 2 numbers\nFor varlen mode: provide 2 or more numbers\nSupported formats:\n- Hexadecimal: 0x01020304 (must use 0x prefix)\n- Decimal: 16909060\n- Octal: 0100402404 (must use 0 prefix)")]
+    /// Encoding used to print the resulting digest
+    #[arg(long, value_enum, default_value_t = OutputFormat::Hex)]
+    output_format: OutputFormat,
+
+    /// Prepend the conventional marker for the chosen format (0x for hex, 0b for binary; other formats have none)
+    #[arg(long)]
+    prefix: bool,
+
+    /// Hash raw bytes read from this file (use '-' for stdin) instead of numeric inputs
+    #[arg(long, value_name = "PATH", conflicts_with = "inputs")]
+    file: Option<String>,
+
+    /// Byte order used when packing 8-byte chunks read from --file into field elements
+    #[arg(long, value_enum, default_value_t = Endian::Little)]
+    endian: Endian,
+
+    /// Input numbers (hex with 0x prefix, decimal, binary with 0b prefix, or octal with 0o prefix)
+    #[arg(help = "Input numbers.\nFor pair mode: provide exactly 2 operands, each either a scalar or a full digest\n  given as 5 comma-separated limbs (a,b,c,d,e)\nFor varlen mode: provide 2 or more numbers\nSupported formats:\n- Hexadecimal: 0x01020304 (must use 0x prefix)\n- Decimal: 16909060\n- Binary: 0b1010 (must use 0b prefix)\n- Octal: 0o100402404 (must use 0o prefix)\n`_` digit separators are allowed, e.g. 0x0102_0304\nOmit these and run the `interactive` subcommand instead to hash repeatedly.")]
     inputs: Vec<String>,
 }
 
+/// Strips `_` digit separators so the remaining text can be handed to
+/// `u64::from_str_radix`.
+fn strip_separators(digits: &str) -> String {
+    digits.replace('_', "")
+}
+
+fn hex_literal(input: &str) -> IResult<&str, u64> {
+    preceded(
+        alt((tag("0x"), tag("0X"))),
+        map_res(
+            take_while1(|c: char| c.is_ascii_hexdigit() || c == '_'),
+            |s: &str| u64::from_str_radix(&strip_separators(s), 16),
+        ),
+    )(input)
+}
+
+fn bin_literal(input: &str) -> IResult<&str, u64> {
+    preceded(
+        alt((tag("0b"), tag("0B"))),
+        map_res(
+            take_while1(|c: char| c == '0' || c == '1' || c == '_'),
+            |s: &str| u64::from_str_radix(&strip_separators(s), 2),
+        ),
+    )(input)
+}
+
+fn oct_literal(input: &str) -> IResult<&str, u64> {
+    preceded(
+        alt((tag("0o"), tag("0O"))),
+        map_res(
+            take_while1(|c: char| ('0'..='7').contains(&c) || c == '_'),
+            |s: &str| u64::from_str_radix(&strip_separators(s), 8),
+        ),
+    )(input)
+}
+
+fn dec_literal(input: &str) -> IResult<&str, u64> {
+    map_res(
+        take_while1(|c: char| c.is_ascii_digit() || c == '_'),
+        |s: &str| strip_separators(s).parse::<u64>(),
+    )(input)
+}
+
+/// Recognizes an optional radix prefix (`0x`/`0X` hex, `0b`/`0B` binary,
+/// `0o`/`0O` octal, bare digits decimal), with `_` digit separators allowed
+/// inside the mantissa. Hex/binary/octal are tried before decimal since they
+/// are gated behind an explicit prefix tag.
+fn numeric_literal(input: &str) -> IResult<&str, u64> {
+    alt((hex_literal, bin_literal, oct_literal, dec_literal))(input)
+}
+
 fn parse_number(input: &str) -> Result<BFieldElement, Box<dyn Error>> {
-    let value = if input.starts_with("0x") || input.starts_with("0X") {
-        // Handle hex format
-        let hex_str = &input[2..];
-        if hex_str.len() % 2 != 0 {
-            return Err("Hex string length must be even (full bytes)".into());
-        }
-        u64::from_str_radix(hex_str, 16)?
-    } else if input.starts_with('0') {
-        // Handle octal
-        let oct_str = &input[1..];
-        u64::from_str_radix(oct_str, 8)?
+    let (_, value) = all_consuming(numeric_literal)(input)
+        .map_err(|e| format!("'{}' is not a valid number: {}", input, e))?;
+    if value >= BFieldElement::P {
+        return Err(format!(
+            "value {} not in field (must be less than {})",
+            value,
+            BFieldElement::P
+        )
+        .into());
+    }
+    Ok(BFieldElement::new(value))
+}
+
+/// Serializes the five `BFieldElement`s of a `Digest` into their 40-byte
+/// little-endian representation.
+fn digest_to_bytes(digest: &Digest) -> [u8; 40] {
+    let mut bytes = [0u8; 40];
+    for (i, element) in digest.values().iter().enumerate() {
+        bytes[i * 8..(i + 1) * 8].copy_from_slice(&element.value().to_le_bytes());
+    }
+    bytes
+}
+
+/// Prints a status/banner line, routing it to stderr instead of stdout when
+/// the output format is `RawBytes` so that stdout carries nothing but the
+/// raw digest bytes and can be piped straight into a binary-consuming tool.
+fn emit_status(message: &str, format: OutputFormat) {
+    if format == OutputFormat::RawBytes {
+        eprintln!("{}", message);
     } else {
-        // Handle decimal
-        input.parse::<u64>()?
+        println!("{}", message);
+    }
+}
+
+/// Prints `hash` using the requested output format, optionally prepending
+/// the conventional marker for that format (`0x` for hex, `0b` for binary;
+/// `prefix` is ignored for the other formats).
+fn print_hash(hash: &Digest, format: OutputFormat, prefix: bool) {
+    match format {
+        OutputFormat::Hex => {
+            let encoded = hex::encode(digest_to_bytes(hash));
+            println!("{}{}", if prefix { "0x" } else { "" }, encoded);
+        }
+        OutputFormat::Base64 => {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(digest_to_bytes(hash));
+            println!("{}", encoded);
+        }
+        OutputFormat::Base32 => {
+            let encoded = data_encoding::BASE32.encode(&digest_to_bytes(hash));
+            println!("{}", encoded);
+        }
+        OutputFormat::Decimal => {
+            let limbs: Vec<String> = hash.values().iter().map(|e| e.value().to_string()).collect();
+            println!("{}", limbs.join(", "));
+        }
+        OutputFormat::Binary => {
+            let encoded: String = digest_to_bytes(hash).iter().map(|b| format!("{:08b}", b)).collect();
+            println!("{}{}", if prefix { "0b" } else { "" }, encoded);
+        }
+        OutputFormat::RawBytes => {
+            std::io::stdout()
+                .write_all(&digest_to_bytes(hash))
+                .expect("failed to write raw bytes to stdout");
+        }
+    }
+}
+
+/// Packs raw bytes into `BFieldElement`s, 8 bytes per element in the given
+/// byte order. A trailing partial chunk is zero-extended, and a final
+/// element carrying the exact byte length is *always* appended (not only
+/// when padding was applied) so that the value of that final element pins
+/// down how many trailing bytes were padding vs. real data, keeping the
+/// mapping collision-free across byte lengths that share the same full
+/// chunks. Each chunk goes through `BFieldElement::new`, which reduces it
+/// modulo p, so a chunk whose u64 value is p or more maps to the same
+/// element as its reduced value and cannot be recovered from the output.
+fn bytes_to_elements(bytes: &[u8], endian: Endian) -> Vec<BFieldElement> {
+    let to_u64 = |chunk: [u8; 8]| match endian {
+        Endian::Big => u64::from_be_bytes(chunk),
+        Endian::Little => u64::from_le_bytes(chunk),
     };
-    Ok(BFieldElement::new(value))
+
+    let mut chunks = bytes.chunks_exact(8);
+    let mut elements: Vec<BFieldElement> = chunks
+        .by_ref()
+        .map(|chunk| BFieldElement::new(to_u64(chunk.try_into().unwrap())))
+        .collect();
+
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() {
+        let mut padded = [0u8; 8];
+        padded[..remainder.len()].copy_from_slice(remainder);
+        elements.push(BFieldElement::new(to_u64(padded)));
+    }
+    elements.push(BFieldElement::new(bytes.len() as u64));
+
+    elements
 }
 
-fn print_hash(hash: &Digest) {
-    println!("{:?}", hash);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_to_elements_is_collision_free_across_a_chunk_boundary() {
+        // Before the length tag was made unconditional, a full 8-byte chunk
+        // of zeros followed by a second full chunk encoding 3 collided with a
+        // standalone 3-byte all-zero input: both produced the element
+        // sequence [0, 3].
+        let two_chunks = bytes_to_elements(
+            &[0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0],
+            Endian::Little,
+        );
+        let short_input = bytes_to_elements(&[0, 0, 0], Endian::Little);
+        assert_ne!(two_chunks, short_input);
+    }
+
+    #[test]
+    fn bytes_to_elements_length_tag_is_always_present() {
+        let exact_multiple = bytes_to_elements(&[0u8; 16], Endian::Little);
+        assert_eq!(exact_multiple.len(), 3);
+        assert_eq!(exact_multiple[2], BFieldElement::new(16));
+
+        let with_remainder = bytes_to_elements(&[0u8; 3], Endian::Little);
+        assert_eq!(with_remainder.len(), 2);
+        assert_eq!(with_remainder[1], BFieldElement::new(3));
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+/// Parses a pair-mode operand. A bare number becomes a digest with that
+/// scalar in the first limb and zeros elsewhere; five comma-separated
+/// numbers (`a,b,c,d,e`) become the digest's limbs directly, so digests
+/// produced by a previous hash can be fed straight back in.
+fn parse_digest_operand(input: &str) -> Result<Digest, Box<dyn Error>> {
+    if input.contains(',') {
+        let parts: Vec<&str> = input.split(',').collect();
+        if parts.len() != 5 {
+            return Err(format!(
+                "digest operand '{}' must have exactly 5 comma-separated limbs",
+                input
+            )
+            .into());
+        }
 
-    match args.mode {
+        let mut limbs = [BFieldElement::new(0); 5];
+        for (limb, part) in limbs.iter_mut().zip(parts.iter()) {
+            *limb = parse_number(part)?;
+        }
+        Ok(Digest::new(limbs))
+    } else {
+        let scalar = parse_number(input)?;
+        Ok(Digest::new([scalar, BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0)]))
+    }
+}
+
+/// Evaluates a mode/inputs pair into a `Digest`. This is the single
+/// evaluation core shared by the argv path and the interactive REPL.
+fn compute(mode: Mode, inputs: &[String]) -> Result<Digest, Box<dyn Error>> {
+    match mode {
         Mode::Pair => {
-            if args.inputs.len() != 2 {
+            if inputs.len() != 2 {
                 return Err("pair mode requires exactly 2 inputs".into());
             }
 
-            let input1 = parse_number(&args.inputs[0])?;
-            let input2 = parse_number(&args.inputs[1])?;
+            let digest1 = parse_digest_operand(&inputs[0])?;
+            let digest2 = parse_digest_operand(&inputs[1])?;
 
-            println!("Hash pair mode [{}, {}]:", args.inputs[0], args.inputs[1]);
-            let result = Tip5::hash_pair(
-                Digest::new([input1, BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0)]),
-                Digest::new([input2, BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0), BFieldElement::new(0)])
-            );
-            print!("Result: ");
-            print_hash(&result);
+            Ok(Tip5::hash_pair(digest1, digest2))
         }
         Mode::Varlen => {
-            if args.inputs.len() < 2 {
+            if inputs.len() < 2 {
                 return Err("varlen mode requires at least 2 inputs".into());
             }
 
-            let mut inputs = Vec::new();
-            for input in &args.inputs {
-                inputs.push(parse_number(input)?);
+            let mut elements = Vec::new();
+            for input in inputs {
+                elements.push(parse_number(input)?);
             }
 
-            print!("Hash varlen mode [");
-            for (i, input) in args.inputs.iter().enumerate() {
-                if i > 0 {
-                    print!(", ");
+            Ok(Tip5::hash_varlen(&elements))
+        }
+    }
+}
+
+/// Drops the user into a line-editing prompt where each line is evaluated
+/// immediately through the same `compute` core used by the argv path.
+fn run_repl(mut mode: Mode, output_format: OutputFormat, prefix: bool) -> Result<(), Box<dyn Error>> {
+    emit_status(
+        "tip5 interactive mode. Commands: `pair <a> <b>` (each a scalar or a,b,c,d,e digest), `varlen <a> <b> ...`, `mode pair|varlen`, bare numbers to hash with the current mode, `exit` to quit.",
+        output_format,
+    );
+
+    let mut editor = rustyline::DefaultEditor::new()?;
+    loop {
+        match editor.readline(&format!("tip5 ({:?})> ", mode)) {
+            Ok(line) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(line);
+
+                if line == "exit" || line == "quit" {
+                    break;
+                }
+
+                let tokens: Vec<String> = line.split_whitespace().map(String::from).collect();
+                match tokens[0].as_str() {
+                    "mode" => match tokens.get(1).map(String::as_str) {
+                        Some("pair") => {
+                            mode = Mode::Pair;
+                            emit_status("default mode set to pair", output_format);
+                        }
+                        Some("varlen") => {
+                            mode = Mode::Varlen;
+                            emit_status("default mode set to varlen", output_format);
+                        }
+                        _ => emit_status("usage: mode pair|varlen", output_format),
+                    },
+                    "pair" => match compute(Mode::Pair, &tokens[1..]) {
+                        Ok(digest) => print_hash(&digest, output_format, prefix),
+                        Err(e) => emit_status(&format!("error: {}", e), output_format),
+                    },
+                    "varlen" => match compute(Mode::Varlen, &tokens[1..]) {
+                        Ok(digest) => print_hash(&digest, output_format, prefix),
+                        Err(e) => emit_status(&format!("error: {}", e), output_format),
+                    },
+                    _ => match compute(mode, &tokens) {
+                        Ok(digest) => print_hash(&digest, output_format, prefix),
+                        Err(e) => emit_status(&format!("error: {}", e), output_format),
+                    },
                 }
-                print!("{}", input);
             }
-            println!("]:");
+            Err(rustyline::error::ReadlineError::Interrupted) => continue,
+            Err(rustyline::error::ReadlineError::Eof) => break,
+            Err(e) => {
+                println!("error: {}", e);
+                break;
+            }
+        }
+    }
 
-            let result = Tip5::hash_varlen(&inputs);
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    if let Some(Command::Interactive) = args.command {
+        return run_repl(args.mode, args.output_format, args.prefix);
+    }
+
+    if let Some(path) = &args.file {
+        let bytes = if path == "-" {
+            let mut buf = Vec::new();
+            std::io::stdin().read_to_end(&mut buf)?;
+            buf
+        } else {
+            std::fs::read(path)?
+        };
+
+        let elements = bytes_to_elements(&bytes, args.endian);
+        emit_status(
+            &format!("Hash file input [{} bytes, {:?}-endian]:", bytes.len(), args.endian),
+            args.output_format,
+        );
+        let result = Tip5::hash_varlen(&elements);
+        if args.output_format != OutputFormat::RawBytes {
             print!("Result: ");
-            print_hash(&result);
         }
+        print_hash(&result, args.output_format, args.prefix);
+        return Ok(());
+    }
+
+    if args.inputs.is_empty() {
+        return Err("no inputs provided; pass numbers or run the `interactive` subcommand".into());
+    }
+
+    // Parse and validate before printing anything, so a rejected operand
+    // never produces success-looking banner output on stdout.
+    let result = compute(args.mode, &args.inputs)?;
+
+    match args.mode {
+        Mode::Pair => {
+            emit_status(
+                &format!("Hash pair mode [{}, {}]:", args.inputs[0], args.inputs[1]),
+                args.output_format,
+            );
+        }
+        Mode::Varlen => {
+            emit_status(&format!("Hash varlen mode [{}]:", args.inputs.join(", ")), args.output_format);
+        }
+    }
+
+    if args.output_format != OutputFormat::RawBytes {
+        print!("Result: ");
     }
+    print_hash(&result, args.output_format, args.prefix);
 
     Ok(())
 }